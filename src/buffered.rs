@@ -0,0 +1,122 @@
+use crate::{get_byte_at, orient_tile, Error, Orientation, MAX7219};
+use embedded_hal_1::digital::OutputPin;
+use embedded_hal_async::spi::SpiBus;
+
+/// A buffered wrapper around `MAX7219` for a chain of `N` devices. It keeps a shadow `[[u8; 8]; N]`
+/// of the frame to display in RAM, plus a per-line dirty bitmask. Drawing methods only
+/// mutate the shadow buffer and mark the lines they touched as dirty; nothing reaches the bus until
+/// `flush` is called, and `flush` only re-emits the registers whose dirty bit is set. This is much
+/// cheaper than the immediate-mode methods on `MAX7219` when animating or updating a few pixels at
+/// a time on a slow bus. Use `MAX7219` directly if you don't want the RAM cost.
+pub struct MAX7219Buffered<'a, CS, const N: usize> {
+    device: MAX7219<'a, CS>,
+    buffer: [[u8; 8]; N],
+    dirty: u8,
+}
+
+impl<'a, CS, PinError, const N: usize> MAX7219Buffered<'a, CS, N>
+where
+    CS: OutputPin<Error = PinError>,
+{
+    pub fn new(cs: &'a mut CS) -> Self {
+        MAX7219Buffered {
+            device: MAX7219::new(cs, N),
+            buffer: [[0; 8]; N],
+            dirty: 0,
+        }
+    }
+
+    /// Gets the number of devices in the chain
+    pub fn get_num_devices(&self) -> usize {
+        N
+    }
+
+    /// Sets the rotation applied to each device's tile before it is latched. Defaults to
+    /// `Orientation::Rotate0`
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.device.set_orientation(orientation);
+    }
+
+    /// Mirrors each device's tile left-to-right before it is latched
+    pub fn set_flip_horizontal(&mut self, flip_horizontal: bool) {
+        self.device.set_flip_horizontal(flip_horizontal);
+    }
+
+    /// Mirrors each device's tile top-to-bottom before it is latched
+    pub fn set_flip_vertical(&mut self, flip_vertical: bool) {
+        self.device.set_flip_vertical(flip_vertical);
+    }
+
+    /// Indicates that the chain is stacked top-to-bottom rather than left-to-right. Many
+    /// modules are wired turned 90 degrees in this configuration, so this applies an extra
+    /// rotation to each tile (on top of `orientation`) to compensate. Defaults to `false`
+    pub fn set_vertical(&mut self, vertical: bool) {
+        self.device.set_vertical(vertical);
+    }
+
+    /// Clears the shadow buffer and marks every line dirty. Call `flush` to push the blank frame
+    /// to the chips
+    pub fn clear_all(&mut self) {
+        self.buffer = [[0; 8]; N];
+        self.dirty = 0xFF;
+    }
+
+    /// Writes a string into the shadow buffer at a pixel position, marking any changed lines
+    /// dirty. Mirrors `MAX7219::write_str_at_pos` but targets the buffer instead of the bus.
+    /// x is the pixel position in the horizontal direction and can be negative.
+    pub fn write_str_at_pos(&mut self, s: &str, x_pos: i32) {
+        let string = s.as_bytes();
+        let shift_by_bits = (x_pos % 8) as i8;
+        let start_string_index = x_pos / 8;
+
+        for chip_index in 0..N {
+            let string_index = N as i32 - chip_index as i32 - 1 - start_string_index;
+
+            let mut tile = [0u8; 8];
+            if string_index >= 0 && string_index <= string.len() as i32 {
+                for (row, tile_line) in tile.iter_mut().enumerate() {
+                    *tile_line = get_byte_at(string, string_index as usize, row, shift_by_bits);
+                }
+            }
+
+            let tile = orient_tile(
+                tile,
+                self.device.orientation,
+                self.device.flip_horizontal,
+                self.device.flip_vertical,
+                self.device.vertical,
+            );
+
+            for (line_index, &val) in tile.iter().enumerate() {
+                if self.buffer[chip_index][line_index] != val {
+                    self.buffer[chip_index][line_index] = val;
+                    self.dirty |= 1 << line_index;
+                }
+            }
+        }
+    }
+
+    /// Pushes only the lines marked dirty to the chips, then clears the dirty mask
+    pub async fn flush<B>(&mut self, spi: &mut B) -> Result<(), Error<B::Error, PinError>>
+    where
+        B: SpiBus,
+    {
+        for line_index in 0..8u8 {
+            if self.dirty & (1 << line_index) == 0 {
+                continue;
+            }
+
+            let mut payload = [0u8; N];
+            for (chip_index, tile) in self.buffer.iter().enumerate() {
+                payload[chip_index] = tile[line_index as usize];
+            }
+
+            self.device
+                .write_line_raw(spi, line_index, &payload)
+                .await?;
+        }
+
+        self.dirty = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Optional `embedded-graphics` integration. Lets callers draw arbitrary shapes, bitmaps and other
+//! fonts onto the daisy chain instead of only CP437 strings, by treating the chain as a single
+//! `N * 8` by `8` pixel 1-bit display (or `8` by `N * 8` for a vertical chain).
+
+use crate::{orient_tile, Error, MAX7219};
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, OriginDimensions, Point, Size},
+    Pixel,
+};
+use embedded_hal_1::digital::OutputPin;
+use embedded_hal_async::spi::SpiBus;
+
+/// An in-memory `N * 8` by `8` pixel framebuffer (`8` by `N * 8` when created with
+/// `new_vertical`) for a daisy chain of `N` devices, one `[u8; 8]` tile per device. Draw to it with the `embedded-graphics` APIs and push it to the chain with
+/// `flush`.
+pub struct FrameBuffer<const N: usize> {
+    tiles: [[u8; 8]; N],
+    /// when true, the chain is laid out as an N-tall vertical stack (8 columns by N*8 rows)
+    /// instead of the default N-wide horizontal row (N*8 columns by 8 rows)
+    vertical: bool,
+}
+
+impl<const N: usize> FrameBuffer<N> {
+    /// Creates a new, blank framebuffer for a horizontal (left-to-right) chain
+    pub fn new() -> Self {
+        FrameBuffer {
+            tiles: [[0; 8]; N],
+            vertical: false,
+        }
+    }
+
+    /// Creates a new, blank framebuffer for a vertical (top-to-bottom) chain
+    pub fn new_vertical() -> Self {
+        FrameBuffer {
+            tiles: [[0; 8]; N],
+            vertical: true,
+        }
+    }
+
+    /// Pushes the buffer to the chips, one register (line) at a time, following the same
+    /// register = line_index + 1, one byte per device pattern as `write_line_raw`. Each device's
+    /// tile is rotated/flipped according to `max7219`'s orientation before it is latched
+    pub async fn flush<B, CS, PinError>(
+        &self,
+        max7219: &mut MAX7219<'_, CS>,
+        spi: &mut B,
+    ) -> Result<(), Error<B::Error, PinError>>
+    where
+        B: SpiBus,
+        CS: OutputPin<Error = PinError>,
+    {
+        for line_index in 0..8u8 {
+            let mut payload = [0u8; N];
+            for (device_index, tile) in self.tiles.iter().enumerate() {
+                let tile = orient_tile(
+                    *tile,
+                    max7219.orientation,
+                    max7219.flip_horizontal,
+                    max7219.flip_vertical,
+                    max7219.vertical,
+                );
+                // earlier-shifted bytes land on the chip furthest down the chain, so device 0
+                // (the near/first chip, as used by write_str_at_pos/write_device_raw) must be
+                // shifted out last
+                payload[N - 1 - device_index] = tile[line_index as usize];
+            }
+            max7219.write_line_raw(spi, line_index, &payload).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for FrameBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> OriginDimensions for FrameBuffer<N> {
+    fn size(&self) -> Size {
+        if self.vertical {
+            Size::new(8, N as u32 * 8)
+        } else {
+            Size::new(N as u32 * 8, 8)
+        }
+    }
+}
+
+impl<const N: usize> DrawTarget for FrameBuffer<N> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = self.size();
+
+        for Pixel(Point { x, y }, color) in pixels {
+            if x < 0 || y < 0 || x as u32 >= size.width || y as u32 >= size.height {
+                continue;
+            }
+
+            let (device_index, row, col) = if self.vertical {
+                (y as usize / 8, y as usize % 8, x as usize)
+            } else {
+                (x as usize / 8, y as usize, x as usize % 8)
+            };
+            let mask = 0x80 >> col;
+
+            if color.is_on() {
+                self.tiles[device_index][row] |= mask;
+            } else {
+                self.tiles[device_index][row] &= !mask;
+            }
+        }
+
+        Ok(())
+    }
+}
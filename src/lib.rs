@@ -6,11 +6,44 @@
 /// see http://www.gammon.com.au/forum/?id=11516 a description of this chip and uses
 /// see also https://github.com/nickgammon/MAX7219 for a simple c based driver
 /// see https://github.com/ninjasource/rp-pico2w-examples for demo of this driver
+/// By default this crate targets blocking `embedded-hal` SPI buses. Enable the `async` feature to
+/// target `embedded-hal-async` instead, which also unlocks `MAX7219Buffered`, `MAX7219Device` and
+/// (with `embedded-graphics` too) `FrameBuffer`.
 use core::result::Result;
+#[cfg(feature = "async")]
+mod buffered;
 mod font;
+#[cfg(all(feature = "async", feature = "embedded-graphics"))]
+mod graphics;
+mod rotate;
+#[cfg(feature = "async")]
+mod spi_device;
 use embedded_hal_1::digital::OutputPin;
+#[cfg(not(feature = "async"))]
+use embedded_hal_1::spi::SpiBus;
+#[cfg(feature = "async")]
 use embedded_hal_async::spi::SpiBus;
 use font::*;
+use rotate::rotate_90_clockwise;
+
+#[cfg(feature = "async")]
+pub use buffered::MAX7219Buffered;
+#[cfg(all(feature = "async", feature = "embedded-graphics"))]
+pub use graphics::FrameBuffer;
+#[cfg(feature = "async")]
+pub use spi_device::MAX7219Device;
+
+/// Display orientation, applied to each device's 8x8 tile before it is latched. Pick whichever
+/// value matches the way your modules are physically wired, so callers don't need to pre-rotate
+/// their own data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
 
 #[derive(Debug)]
 pub enum Error<SpiError, PinError> {
@@ -46,15 +79,33 @@ pub enum Command {
 pub struct MAX7219<'a, CS> {
     cs: &'a mut CS,
     num_devices: usize,
+    orientation: Orientation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    vertical: bool,
 }
 
 /// we are using v2 flavour of the embedded_hal OutputPin here with its error handling
+///
+/// Every method below is written once using `async`/`.await`. The `maybe_async_cfg::maybe`
+/// attribute emits it twice: unchanged when the `async` feature is enabled (against
+/// `embedded_hal_async::spi::SpiBus`), and with `async`/`.await` stripped by default (against the
+/// blocking `embedded_hal::spi::SpiBus`), so there is a single body to maintain for both HAL
+/// flavours.
+#[maybe_async_cfg::maybe(sync(cfg(not(feature = "async")), keep_self), async(feature = "async", keep_self))]
 impl<'a, CS, PinError> MAX7219<'a, CS>
 where
     CS: OutputPin<Error = PinError>,
 {
     pub fn new(cs: &'a mut CS, num_devices: usize) -> Self {
-        MAX7219 { cs, num_devices }
+        MAX7219 {
+            cs,
+            num_devices,
+            orientation: Orientation::default(),
+            flip_horizontal: false,
+            flip_vertical: false,
+            vertical: false,
+        }
     }
 
     /// Gets the number of devices you passed in when calling new
@@ -62,6 +113,36 @@ where
         self.num_devices
     }
 
+    /// Sets the rotation applied to each device's tile before it is latched. Defaults to
+    /// `Orientation::Rotate0`
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Mirrors each device's tile left-to-right before it is latched
+    pub fn set_flip_horizontal(&mut self, flip_horizontal: bool) {
+        self.flip_horizontal = flip_horizontal;
+    }
+
+    /// Mirrors each device's tile top-to-bottom before it is latched
+    pub fn set_flip_vertical(&mut self, flip_vertical: bool) {
+        self.flip_vertical = flip_vertical;
+    }
+
+    /// Indicates that the chain is stacked top-to-bottom rather than left-to-right. Many
+    /// modules are wired turned 90 degrees in this configuration, so this applies an extra
+    /// rotation to each tile (on top of `orientation`) to compensate. Defaults to `false`
+    pub fn set_vertical(&mut self, vertical: bool) {
+        self.vertical = vertical;
+    }
+
+    fn is_identity_orientation(&self) -> bool {
+        self.orientation == Orientation::Rotate0
+            && !self.flip_horizontal
+            && !self.flip_vertical
+            && !self.vertical
+    }
+
     /// Write command to all chips
     pub async fn write_command_all<B>(
         &mut self,
@@ -201,15 +282,29 @@ where
                 // write the string backwards because we push bytes onto the bus so the last
                 // character appears first
                 let string_index =
-                    self.num_devices as i32 - chip_index as i32 - 1 - start_string_index as i32;
+                    self.num_devices as i32 - chip_index as i32 - 1 - start_string_index;
                 let register = line_index as u8 + 1;
                 self.shift_out(spi, register).await?;
 
                 // bit of a strange range check here but we need to draw the remainder of the last character
                 if string_index >= 0 && string_index <= string.len() as i32 {
                     // we may need to draw a single character over two chips so we need to do some bit shifting
-                    let val =
-                        self.get_byte_at(string, string_index as usize, line_index, shift_by_bits);
+                    let val = if self.is_identity_orientation() {
+                        get_byte_at(string, string_index as usize, line_index, shift_by_bits)
+                    } else {
+                        let mut tile = [0u8; 8];
+                        for (row, tile_line) in tile.iter_mut().enumerate() {
+                            *tile_line =
+                                get_byte_at(string, string_index as usize, row, shift_by_bits);
+                        }
+                        orient_tile(
+                            tile,
+                            self.orientation,
+                            self.flip_horizontal,
+                            self.flip_vertical,
+                            self.vertical,
+                        )[line_index]
+                    };
                     self.shift_out(spi, val).await?;
                 } else {
                     self.shift_out(spi, 0).await?;
@@ -223,48 +318,73 @@ where
         Ok(())
     }
 
-    /// gets a byte representing part of a font character shifted by some number of bits
-    /// it is possible to get part of the next or previous character returned because of the
-    /// position shifting
-    fn get_byte_at(
+    /// Proportional (variable-width) version of `write_str_at_pos`. Each CP437 glyph is trimmed of
+    /// its leading and trailing blank columns and a single blank interspace column is inserted
+    /// between characters, so narrow glyphs like `i` and `.` don't waste horizontal space. This
+    /// makes scrolling text look less sparse at the cost of a few extra lookups per line.
+    /// x is the pixel position in the horizontal direction and can be negative.
+    pub async fn write_str_at_pos_proportional<B>(
         &mut self,
-        string: &[u8],
-        string_index: usize,
-        line_index: usize,
-        shift_by_num_bits: i8,
-    ) -> u8 {
-        let left_index = string_index as i32 - 1;
-        let mid_index = string_index;
-        let right_index = string_index + 1;
-        let len = string.len() as i32;
-
-        let left = if is_in_range(len, left_index) {
-            CP437FONT[string[left_index as usize] as usize]
-        } else {
-            CP437FONT[0]
-        };
-        let middle = if is_in_range(len, mid_index as i32) {
-            CP437FONT[string[mid_index] as usize]
-        } else {
-            CP437FONT[0]
-        };
-        let right = if is_in_range(len, right_index as i32) {
-            CP437FONT[string[right_index] as usize]
-        } else {
-            CP437FONT[0]
-        };
-
-        if shift_by_num_bits == 0 {
-            middle[line_index]
-        } else if shift_by_num_bits < 0 {
-            // shift digit left
-            let shift_by_num_bits = -shift_by_num_bits as u8;
-            middle[line_index] >> shift_by_num_bits ^ right[line_index] << (8 - shift_by_num_bits)
-        } else {
-            // shift digit right
-            let shift_by_num_bits = shift_by_num_bits as u8;
-            middle[line_index] << shift_by_num_bits ^ left[line_index] >> (8 - shift_by_num_bits)
+        spi: &mut B,
+        s: &str,
+        x_pos: i32,
+    ) -> Result<(), Error<B::Error, PinError>>
+    where
+        B: SpiBus,
+    {
+        let string = s.as_bytes();
+
+        for line_index in 0..8 {
+            self.cs.set_low().map_err(Error::Pin)?;
+
+            for chip_index in 0..self.num_devices {
+                let register = line_index as u8 + 1;
+                self.shift_out(spi, register).await?;
+
+                // device_base is the column (in proportional string space) of this device's
+                // leftmost pixel when x_pos is zero, mirroring the chip ordering used by
+                // write_str_at_pos
+                let device_base = (self.num_devices - chip_index - 1) as i32 * 8;
+                // rows_needed covers just the current line in the common (identity orientation)
+                // case, or the whole tile when a rotation/flip needs every row to compose one
+                let rows_needed = if self.is_identity_orientation() {
+                    line_index..line_index + 1
+                } else {
+                    0..8
+                };
+
+                let mut tile = [0u8; 8];
+                for bit in 0..8u8 {
+                    let col = device_base + bit as i32 - x_pos;
+                    if let Some((char_index, glyph_col)) = proportional_char_at(string, col) {
+                        let glyph = CP437FONT[string[char_index] as usize];
+                        for row in rows_needed.clone() {
+                            if glyph[row] & (0x80 >> glyph_col) != 0 {
+                                tile[row] |= 0x80 >> bit;
+                            }
+                        }
+                    }
+                }
+
+                let val = if self.is_identity_orientation() {
+                    tile[line_index]
+                } else {
+                    orient_tile(
+                        tile,
+                        self.orientation,
+                        self.flip_horizontal,
+                        self.flip_vertical,
+                        self.vertical,
+                    )[line_index]
+                };
+
+                self.shift_out(spi, val).await?;
+            }
+
+            self.cs.set_high().map_err(Error::Pin)?;
         }
+
+        Ok(())
     }
 
     /// sends a byte of data to the spi bus
@@ -286,3 +406,163 @@ where
 fn is_in_range(len: i32, i: i32) -> bool {
     i >= 0 && i < len
 }
+
+/// gets a byte representing part of a font character shifted by some number of bits
+/// it is possible to get part of the next or previous character returned because of the
+/// position shifting
+pub(crate) fn get_byte_at(
+    string: &[u8],
+    string_index: usize,
+    line_index: usize,
+    shift_by_num_bits: i8,
+) -> u8 {
+    let left_index = string_index as i32 - 1;
+    let mid_index = string_index;
+    let right_index = string_index + 1;
+    let len = string.len() as i32;
+
+    let left = if is_in_range(len, left_index) {
+        CP437FONT[string[left_index as usize] as usize]
+    } else {
+        CP437FONT[0]
+    };
+    let middle = if is_in_range(len, mid_index as i32) {
+        CP437FONT[string[mid_index] as usize]
+    } else {
+        CP437FONT[0]
+    };
+    let right = if is_in_range(len, right_index as i32) {
+        CP437FONT[string[right_index] as usize]
+    } else {
+        CP437FONT[0]
+    };
+
+    if shift_by_num_bits == 0 {
+        middle[line_index]
+    } else if shift_by_num_bits < 0 {
+        // shift digit left
+        let shift_by_num_bits = -shift_by_num_bits as u8;
+        middle[line_index] >> shift_by_num_bits ^ right[line_index] << (8 - shift_by_num_bits)
+    } else {
+        // shift digit right
+        let shift_by_num_bits = shift_by_num_bits as u8;
+        middle[line_index] << shift_by_num_bits ^ left[line_index] >> (8 - shift_by_num_bits)
+    }
+}
+
+/// applies a display orientation to a single device's 8x8 tile by composing `rotate_90_clockwise`
+/// and bit-reversal, in preparation for latching
+pub(crate) fn orient_tile(
+    tile: [u8; 8],
+    orientation: Orientation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    vertical: bool,
+) -> [u8; 8] {
+    // modules wired into a vertical (top-to-bottom) chain are typically turned 90 degrees from
+    // the horizontal-chain orientation, so compensate before applying the user's own orientation
+    let tile = if vertical {
+        rotate_90_clockwise(tile)
+    } else {
+        tile
+    };
+
+    let mut tile = match orientation {
+        Orientation::Rotate0 => tile,
+        Orientation::Rotate90 => rotate_90_clockwise(tile),
+        Orientation::Rotate180 => rotate_90_clockwise(rotate_90_clockwise(tile)),
+        Orientation::Rotate270 => {
+            rotate_90_clockwise(rotate_90_clockwise(rotate_90_clockwise(tile)))
+        }
+    };
+
+    if flip_vertical {
+        tile.reverse();
+    }
+
+    if flip_horizontal {
+        for line in tile.iter_mut() {
+            *line = line.reverse_bits();
+        }
+    }
+
+    tile
+}
+
+/// ORs together all 8 row-bytes of a glyph to find which column bit positions are ever set
+fn glyph_column_mask(glyph: &[u8; 8]) -> u8 {
+    glyph.iter().fold(0u8, |acc, line| acc | line)
+}
+
+/// the inclusive (leftmost, rightmost) set column of a glyph, column 0 being the MSB (leftmost
+/// on the display). a blank glyph (eg space) has no set columns so we fall back to a narrow gap
+fn glyph_bounds(glyph: &[u8; 8]) -> (u8, u8) {
+    let mask = glyph_column_mask(glyph);
+    if mask == 0 {
+        return (3, 4);
+    }
+
+    let mut left = 0;
+    while mask & (0x80 >> left) == 0 {
+        left += 1;
+    }
+
+    let mut right = 7;
+    while mask & (0x80 >> right) == 0 {
+        right -= 1;
+    }
+
+    (left, right)
+}
+
+/// maps a column in proportional string space (column 0 is the first pixel of the first
+/// character's trimmed glyph) to the character that owns it and the column within that
+/// character's trimmed glyph. returns None if the column falls in the inter-character interspace
+/// or outside the string
+fn proportional_char_at(string: &[u8], col: i32) -> Option<(usize, u8)> {
+    if col < 0 {
+        return None;
+    }
+
+    let mut offset = 0i32;
+    for (char_index, &ch) in string.iter().enumerate() {
+        let (left, right) = glyph_bounds(&CP437FONT[ch as usize]);
+        let width = (right - left + 1) as i32;
+
+        if col < offset + width {
+            return Some((char_index, left + (col - offset) as u8));
+        }
+
+        offset += width + 1; // one blank interspace column after each glyph
+        if col < offset {
+            return None; // inside the interspace
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_bounds_blank_glyph_falls_back_to_narrow_gap() {
+        let blank = [0u8; 8];
+        assert_eq!(glyph_bounds(&blank), (3, 4));
+    }
+
+    #[test]
+    fn glyph_bounds_trims_to_the_set_columns() {
+        // columns 2..=5 set, rest blank
+        let glyph = [0b0011_1100; 8];
+        assert_eq!(glyph_bounds(&glyph), (2, 5));
+    }
+
+    #[test]
+    fn glyph_bounds_single_column_glyph() {
+        // only the leftmost column (MSB) is ever set
+        let glyph = [0b1000_0000; 8];
+        assert_eq!(glyph_bounds(&glyph), (0, 0));
+    }
+}
@@ -15,10 +15,38 @@ pub fn rotate_90_clockwise (buffer: [u8; 8]) -> [u8; 8]{
         for j in 0..8 {
             if is_bit_set(*line, j) {
                 let mask: u8 = 1 << i as u8;
-                rotated[7 - j] = rotated[7 - j] | mask;
+                rotated[(7 - j) as usize] |= mask;
             }
         }
     }
 
     rotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four_rotations_are_the_identity() {
+        let tile = [0b1000_0001, 0, 0, 0b0001_1000, 0b0001_1000, 0, 0, 0b1000_0001];
+
+        let rotated = rotate_90_clockwise(rotate_90_clockwise(rotate_90_clockwise(
+            rotate_90_clockwise(tile),
+        )));
+
+        assert_eq!(rotated, tile);
+    }
+
+    #[test]
+    fn rotates_top_left_pixel_to_top_right() {
+        let mut tile = [0u8; 8];
+        tile[0] = 0b1000_0000;
+
+        let rotated = rotate_90_clockwise(tile);
+
+        let mut expected = [0u8; 8];
+        expected[0] = 0b0000_0001;
+        assert_eq!(rotated, expected);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::{get_byte_at, orient_tile, Command, Error, Orientation};
+use embedded_hal_async::spi::SpiDevice;
+
+/// Alternate driver built on `embedded_hal_async::spi::SpiDevice` instead of a raw `SpiBus` + `CS`
+/// pin. The device abstraction owns chip-select and transaction framing, so this driver issues a
+/// single `SpiDevice::write` per latch instead of bracketing N manual `shift_out` calls with
+/// `set_low`/`set_high`. This lets the MAX7219 coexist on a shared bus behind `ExclusiveDevice` or
+/// other bus-manager types. Use `MAX7219` instead if you're on a bare `SpiBus` with your own CS pin.
+///
+/// Every method that writes to the bus takes a `buf` scratch slice that must be at least `2 * num_devices` bytes long
+/// (one register byte and one data byte per device) since this type has no const generic to size
+/// an internal buffer with.
+pub struct MAX7219Device<'a, D> {
+    device: &'a mut D,
+    num_devices: usize,
+    orientation: Orientation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    vertical: bool,
+}
+
+impl<'a, D> MAX7219Device<'a, D>
+where
+    D: SpiDevice,
+{
+    pub fn new(device: &'a mut D, num_devices: usize) -> Self {
+        MAX7219Device {
+            device,
+            num_devices,
+            orientation: Orientation::default(),
+            flip_horizontal: false,
+            flip_vertical: false,
+            vertical: false,
+        }
+    }
+
+    /// Gets the number of devices you passed in when calling new
+    pub fn get_num_devices(&self) -> usize {
+        self.num_devices
+    }
+
+    /// Sets the rotation applied to each device's tile before it is latched. Defaults to
+    /// `Orientation::Rotate0`
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Mirrors each device's tile left-to-right before it is latched
+    pub fn set_flip_horizontal(&mut self, flip_horizontal: bool) {
+        self.flip_horizontal = flip_horizontal;
+    }
+
+    /// Mirrors each device's tile top-to-bottom before it is latched
+    pub fn set_flip_vertical(&mut self, flip_vertical: bool) {
+        self.flip_vertical = flip_vertical;
+    }
+
+    /// Indicates that the chain is stacked top-to-bottom rather than left-to-right. Many
+    /// modules are wired turned 90 degrees in this configuration, so this applies an extra
+    /// rotation to each tile (on top of `orientation`) to compensate. Defaults to `false`
+    pub fn set_vertical(&mut self, vertical: bool) {
+        self.vertical = vertical;
+    }
+
+    fn is_identity_orientation(&self) -> bool {
+        self.orientation == Orientation::Rotate0
+            && !self.flip_horizontal
+            && !self.flip_vertical
+            && !self.vertical
+    }
+
+    /// Write command to all chips in a single transaction
+    pub async fn write_command_all(
+        &mut self,
+        buf: &mut [u8],
+        command: Command,
+        data: u8,
+    ) -> Result<(), Error<D::Error, core::convert::Infallible>> {
+        self.write_raw_all(buf, command as u8, data).await
+    }
+
+    /// Clear the display
+    pub async fn clear_all(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(), Error<D::Error, core::convert::Infallible>> {
+        for register in 1..9 {
+            self.write_raw_all(buf, register, 0).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the same raw byte to all chips in a single transaction
+    pub async fn write_raw_all(
+        &mut self,
+        buf: &mut [u8],
+        register: u8,
+        data: u8,
+    ) -> Result<(), Error<D::Error, core::convert::Infallible>> {
+        if buf.len() < self.num_devices * 2 {
+            return Err(Error::InvalidPayloadLength);
+        }
+
+        for chunk in buf[..self.num_devices * 2].chunks_exact_mut(2) {
+            chunk[0] = register;
+            chunk[1] = data;
+        }
+
+        self.device
+            .write(&buf[..self.num_devices * 2])
+            .await
+            .map_err(Error::Spi)
+    }
+
+    /// Payload should have num_devices number of bytes in it
+    /// line_index should be between 0 and 7 (bottom to top if the led serial number is facing up)
+    pub async fn write_line_raw(
+        &mut self,
+        buf: &mut [u8],
+        line_index: u8,
+        payload: &[u8],
+    ) -> Result<(), Error<D::Error, core::convert::Infallible>> {
+        if line_index >= 8 {
+            return Err(Error::InvalidLineIndex);
+        }
+
+        if payload.len() != self.num_devices || buf.len() < self.num_devices * 2 {
+            return Err(Error::InvalidPayloadLength);
+        }
+
+        let register = line_index + 1;
+        for (chunk, data) in buf.chunks_exact_mut(2).zip(payload) {
+            chunk[0] = register;
+            chunk[1] = *data;
+        }
+
+        self.device
+            .write(&buf[..self.num_devices * 2])
+            .await
+            .map_err(Error::Spi)
+    }
+
+    /// Use this nightmare function to text to the led display at an arbitrary position.
+    /// primarily used for scrolling text
+    /// x is the pixel position in the horizontal direction and can be negative
+    pub async fn write_str_at_pos(
+        &mut self,
+        buf: &mut [u8],
+        s: &str,
+        x_pos: i32,
+    ) -> Result<(), Error<D::Error, core::convert::Infallible>> {
+        if buf.len() < self.num_devices * 2 {
+            return Err(Error::InvalidPayloadLength);
+        }
+
+        let string = s.as_bytes();
+        let shift_by_bits = (x_pos % 8) as i8;
+        let start_string_index = x_pos / 8;
+        let identity_orientation = self.is_identity_orientation();
+
+        for line_index in 0..8usize {
+            for chip_index in 0..self.num_devices {
+                // write the string backwards because we push bytes onto the bus so the last
+                // character appears first
+                let string_index =
+                    self.num_devices as i32 - chip_index as i32 - 1 - start_string_index;
+
+                let val = if string_index >= 0 && string_index <= string.len() as i32 {
+                    if identity_orientation {
+                        get_byte_at(string, string_index as usize, line_index, shift_by_bits)
+                    } else {
+                        let mut tile = [0u8; 8];
+                        for (row, tile_line) in tile.iter_mut().enumerate() {
+                            *tile_line =
+                                get_byte_at(string, string_index as usize, row, shift_by_bits);
+                        }
+                        orient_tile(
+                            tile,
+                            self.orientation,
+                            self.flip_horizontal,
+                            self.flip_vertical,
+                            self.vertical,
+                        )[line_index]
+                    }
+                } else {
+                    0
+                };
+
+                buf[chip_index * 2] = line_index as u8 + 1;
+                buf[chip_index * 2 + 1] = val;
+            }
+
+            self.device
+                .write(&buf[..self.num_devices * 2])
+                .await
+                .map_err(Error::Spi)?;
+        }
+
+        Ok(())
+    }
+}